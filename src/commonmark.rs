@@ -0,0 +1,186 @@
+//! Translates the CommonMark-flavoured Markdown used in nixpkgs doc
+//! comments into DocBook XML, writing events directly into the same
+//! `xml-rs` `EventWriter` used by the rest of the document. This keeps
+//! escaping consistent with the surrounding document instead of
+//! re-implementing it here.
+//!
+//! The mapping from CommonMark nodes to DocBook elements is:
+//!
+//! * paragraph             -> `<para>`
+//! * emphasis               -> `<emphasis>`
+//! * strong emphasis        -> `<emphasis role="strong">`
+//! * inline code            -> `<literal>`
+//! * fenced/indented code   -> `<programlisting>`
+//! * bullet list            -> `<itemizedlist>`
+//! * ordered list           -> `<orderedlist>`
+//! * list item              -> `<listitem>`, with a `<para>` supplied
+//!                              by the item's content (explicit for a
+//!                              loose list, synthesized for a tight one)
+//! * link                   -> `<link xlink:href="...">`
+//!
+//! Block constructs we don't have a DocBook mapping for (headings,
+//! block quotes, tables, images, ...) are dropped along with their
+//! contents, rather than leaking their inner text as bare characters
+//! outside any block element.
+
+use pulldown_cmark::{Event, Parser, Tag};
+use std::io::Write;
+use xml::writer::{EventWriter, XmlEvent};
+
+use crate::Result;
+
+/// Whether we have a DocBook mapping for this tag at all. Anything
+/// else (and everything nested inside it) is skipped entirely.
+fn is_supported(tag: &Tag) -> bool {
+    match tag {
+        Tag::Paragraph
+        | Tag::Emphasis
+        | Tag::Strong
+        | Tag::List(_)
+        | Tag::Item
+        | Tag::CodeBlock(_)
+        | Tag::Link(..) => true,
+        _ => false,
+    }
+}
+
+/// Inline-level tags, i.e. the ones that can appear as the bare first
+/// content of a tight list item and so may need a synthesized `<para>`
+/// (see `open_inline_para`).
+fn is_inline(tag: &Tag) -> bool {
+    match tag {
+        Tag::Emphasis | Tag::Strong | Tag::Link(..) => true,
+        _ => false,
+    }
+}
+
+/// Render a block of Markdown text as DocBook XML into `w`.
+pub fn write_markdown<W: Write>(w: &mut EventWriter<W>, text: &str) -> Result<()> {
+    // For each currently open `<listitem>` (innermost last), whether
+    // we've synthesized a `<para>` around its bare inline content
+    // (tight list) that we're responsible for closing ourselves. Real
+    // `Paragraph` events (loose lists) close themselves and never set
+    // this.
+    let mut item_stack: Vec<bool> = vec![];
+
+    // Depth of paragraph-like content currently open, real or
+    // synthesized, so inline content inside an explicit `Paragraph`
+    // never triggers synthesis of a second, nested one. It counts
+    // only within the innermost open `<listitem>`: entering an item
+    // saves it on `saved_para_depth` and starts again from zero, so
+    // a tight list nested inside a tight item gets its own `<para>`s.
+    let mut para_depth: u32 = 0;
+    let mut saved_para_depth: Vec<u32> = vec![];
+
+    // Nesting depth of an unsupported block tag being skipped; while
+    // greater than zero every event other than its own Start/End
+    // bookkeeping is dropped.
+    let mut skip_depth: u32 = 0;
+
+    for event in Parser::new(text) {
+        if skip_depth > 0 {
+            match event {
+                Event::Start(_) => skip_depth += 1,
+                Event::End(_) => skip_depth -= 1,
+                _ => (),
+            }
+            continue;
+        }
+
+        match event {
+            Event::Start(tag) => {
+                if !is_supported(&tag) {
+                    skip_depth = 1;
+                    continue;
+                }
+
+                match tag {
+                    Tag::Item => {
+                        item_stack.push(false);
+                        saved_para_depth.push(para_depth);
+                        para_depth = 0;
+                        w.write(XmlEvent::start_element("listitem"))?;
+                    }
+                    Tag::Paragraph => {
+                        w.write(XmlEvent::start_element("para"))?;
+                        para_depth += 1;
+                    }
+                    _ if is_inline(&tag) => {
+                        open_inline_para(w, &mut item_stack, &mut para_depth)?;
+                        write_start_tag(w, &tag)?;
+                    }
+                    _ => write_start_tag(w, &tag)?,
+                }
+            }
+            Event::End(tag) => {
+                match tag {
+                    Tag::Item => {
+                        if item_stack.pop() == Some(true) {
+                            w.write(XmlEvent::end_element())?; // </para> (synthesized)
+                            para_depth -= 1;
+                        }
+                        para_depth = saved_para_depth.pop().unwrap_or(0);
+                        w.write(XmlEvent::end_element())?; // </listitem>
+                    }
+                    Tag::Paragraph => {
+                        w.write(XmlEvent::end_element())?;
+                        para_depth -= 1;
+                    }
+                    _ => w.write(XmlEvent::end_element())?,
+                }
+            }
+            Event::Text(text) => {
+                open_inline_para(w, &mut item_stack, &mut para_depth)?;
+                w.write(XmlEvent::characters(&text))?;
+            }
+            Event::Code(code) => {
+                open_inline_para(w, &mut item_stack, &mut para_depth)?;
+                w.write(XmlEvent::start_element("literal"))?;
+                w.write(XmlEvent::characters(&code))?;
+                w.write(XmlEvent::end_element())?;
+            }
+            Event::SoftBreak | Event::HardBreak => w.write(XmlEvent::characters("\n"))?,
+            // Remaining events (raw HTML, footnote references, ...)
+            // have no DocBook mapping and are simply ignored.
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+/// If we're directly inside a tight list item (no `Paragraph` open
+/// yet, real or synthesized) and about to emit inline content, open a
+/// `<para>` on its behalf and remember to close it again when the
+/// item ends.
+fn open_inline_para<W: Write>(w: &mut EventWriter<W>,
+                               item_stack: &mut Vec<bool>,
+                               para_depth: &mut u32) -> Result<()> {
+    if *para_depth == 0 {
+        if let Some(synthesized) = item_stack.last_mut() {
+            if !*synthesized {
+                w.write(XmlEvent::start_element("para"))?;
+                *synthesized = true;
+                *para_depth += 1;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_start_tag<W: Write>(w: &mut EventWriter<W>, tag: &Tag) -> Result<()> {
+    match tag {
+        Tag::Emphasis => w.write(XmlEvent::start_element("emphasis"))?,
+        Tag::Strong => w.write(XmlEvent::start_element("emphasis").attr("role", "strong"))?,
+        Tag::List(None) => w.write(XmlEvent::start_element("itemizedlist"))?,
+        Tag::List(Some(_)) => w.write(XmlEvent::start_element("orderedlist"))?,
+        Tag::CodeBlock(_) => w.write(XmlEvent::start_element("programlisting"))?,
+        Tag::Link(_, url, _) => {
+            w.write(XmlEvent::start_element("link").attr("xlink:href", url.as_ref()))?
+        }
+        _ => return Ok(()),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+//! Pluggable output backends.
+//!
+//! `main` builds one `DocFormat` implementation for whichever format
+//! was requested on the command line, then drives every `ManualEntry`
+//! through it via `write_header`/`write_entry`/`write_footer`. Adding
+//! a future backend (e.g. JSON) is a matter of writing one more impl
+//! and wiring it into `Format::from_str` and `main`.
+
+use std::io::Write;
+use xml::writer::{EmitterConfig, EventWriter, XmlEvent};
+
+use crate::{ManualEntry, Result};
+
+/// A backend capable of rendering a stream of `ManualEntry` values as
+/// a single complete document.
+pub trait DocFormat {
+    fn write_header(&mut self, title: &str) -> Result<()>;
+    fn write_entry(&mut self, entry: &ManualEntry) -> Result<()>;
+    fn write_footer(&mut self) -> Result<()>;
+}
+
+/// Renders entries as a DocBook `<section>`, nixdoc's original output
+/// format and the one consumed by the nixpkgs manual.
+pub struct DocbookWriter<W: Write> {
+    category: String,
+    writer: EventWriter<W>,
+}
+
+impl<W: Write> DocbookWriter<W> {
+    pub fn new(category: String, inner: W) -> Self {
+        DocbookWriter {
+            category,
+            writer: EmitterConfig::new().perform_indent(true).create_writer(inner),
+        }
+    }
+}
+
+impl<W: Write> DocFormat for DocbookWriter<W> {
+    fn write_header(&mut self, title: &str) -> Result<()> {
+        self.writer.write(
+            XmlEvent::start_element("section")
+                .attr("xmlns", "http://docbook.org/ns/docbook")
+                .attr("xmlns:xlink", "http://www.w3.org/1999/xlink")
+                .attr("xmlns:xi", "http://www.w3.org/2001/XInclude")
+                .attr("xml:id", format!("sec-functions-library-{}", self.category).as_str()))?;
+
+        self.writer.write(XmlEvent::start_element("title"))?;
+        self.writer.write(XmlEvent::characters(title))?;
+        self.writer.write(XmlEvent::end_element())?;
+
+        Ok(())
+    }
+
+    fn write_entry(&mut self, entry: &ManualEntry) -> Result<()> {
+        entry.write_section_xml(&mut self.writer)
+    }
+
+    fn write_footer(&mut self) -> Result<()> {
+        self.writer.write(XmlEvent::end_element())?; // </section>
+        Ok(())
+    }
+}
+
+/// Renders entries as Markdown, mirroring the DocBook backend's
+/// structure for tooling that has moved away from DocBook.
+pub struct MarkdownWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> MarkdownWriter<W> {
+    pub fn new(inner: W) -> Self {
+        MarkdownWriter { writer: inner }
+    }
+}
+
+impl<W: Write> DocFormat for MarkdownWriter<W> {
+    fn write_header(&mut self, title: &str) -> Result<()> {
+        writeln!(self.writer, "# {}\n", title)?;
+        Ok(())
+    }
+
+    fn write_entry(&mut self, entry: &ManualEntry) -> Result<()> {
+        entry.write_section_markdown(&mut self.writer)
+    }
+
+    fn write_footer(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
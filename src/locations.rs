@@ -0,0 +1,46 @@
+//! Generates `locations.xml`, a DocBook document linking every
+//! documented library function back to the line in its source file
+//! where it is defined.
+//!
+//! This information used to be produced by a separate script living
+//! in nixpkgs, run after nixdoc itself. Since `collect_entry_information`
+//! already resolves the source position of every entry while parsing,
+//! nixdoc can simply emit this file itself, removing that out-of-band
+//! dependency.
+
+use std::io::Write;
+use xml::writer::{EmitterConfig, EventWriter, XmlEvent};
+
+use crate::{ManualEntry, Result};
+
+/// Write a `locations.xml` document to `w`, containing one linkable
+/// section per entry in `entries`, each pointing at `file` and the
+/// entry's source line.
+pub fn write_locations<W: Write>(w: W, file: &str, entries: &[ManualEntry]) -> Result<()> {
+    let mut writer = EmitterConfig::new()
+        .perform_indent(true)
+        .create_writer(w);
+
+    writer.write(
+        XmlEvent::start_element("chunk")
+            .attr("xmlns", "http://docbook.org/ns/docbook")
+            .attr("xmlns:xlink", "http://www.w3.org/1999/xlink"))?;
+
+    for entry in entries {
+        let ident = format!("lib.{}.{}", entry.category, entry.name);
+
+        writer.write(XmlEvent::start_element("section")
+            .attr("xml:id", format!("function-location-{}", ident).as_str()))?;
+
+        writer.write(XmlEvent::start_element("link")
+            .attr("xlink:href", format!("file://{}#L{}", file, entry.line).as_str()))?;
+        writer.write(XmlEvent::characters(&ident))?;
+        writer.write(XmlEvent::end_element())?; // </link>
+
+        writer.write(XmlEvent::end_element())?; // </section>
+    }
+
+    writer.write(XmlEvent::end_element())?; // </chunk>
+
+    Ok(())
+}
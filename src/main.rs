@@ -3,7 +3,6 @@
 //!
 //! TODO:
 //! * extract function argument names
-//! * extract line number & add it to generated output
 //! * figure out how to specify examples (& leading whitespace?!)
 
 use failure::Error;
@@ -15,10 +14,35 @@ use std::fs;
 use std::io::{self, Write};
 use std::path::PathBuf;
 use structopt::StructOpt;
-use xml::writer::{EventWriter, EmitterConfig, XmlEvent};
+use xml::writer::{EventWriter, XmlEvent};
+
+mod commonmark;
+mod format;
+mod locations;
+
+use format::DocFormat;
 
 type Result<T> = std::result::Result<T, Error>;
 
+/// Output format to render entries as.
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Docbook,
+    Markdown,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        match s {
+            "docbook" => Ok(Format::Docbook),
+            "markdown" => Ok(Format::Markdown),
+            other => Err(format!("unknown format '{}' (expected 'docbook' or 'markdown')", other)),
+        }
+    }
+}
+
 /// Command line arguments for nixdoc
 #[derive(Debug, StructOpt)]
 #[structopt(name = "nixdoc", about = "Generate Docbook from Nix library functions")]
@@ -34,6 +58,31 @@ struct Options {
     /// Description of the function category.
     #[structopt(short = "d", long = "description")]
     description: String,
+
+    /// Path to write a `locations.xml` linking each entry back to its
+    /// source line. If unset, no location information is emitted and
+    /// the `xi:include` in the main document will dangle as before.
+    #[structopt(short = "l", long = "locations", parse(from_os_str))]
+    locations: Option<PathBuf>,
+
+    /// Output format to generate.
+    #[structopt(long = "format", default_value = "docbook", parse(try_from_str))]
+    format: Format,
+}
+
+/// A single named usage example, optionally paired with its expected
+/// evaluation result.
+#[derive(Debug, Clone)]
+struct Example {
+    /// Title given on the `Example:` line, if any.
+    title: Option<String>,
+
+    /// The example expression itself.
+    code: String,
+
+    /// Expected result of evaluating `code`, if given after a `=>` or
+    /// `Result:` separator.
+    result: Option<String>,
 }
 
 #[derive(Debug)]
@@ -44,15 +93,35 @@ struct DocComment {
     /// Optional type annotation for the thing being documented.
     doc_type: Option<String>,
 
-    /// Usage example(s) (interpreted as a single code block)
-    example: Option<String>,
+    /// Usage example(s) for the documented item.
+    examples: Vec<Example>,
+}
+
+/// A single documented function argument. Plain curried arguments
+/// (`a: ...`) only ever have a name; pattern arguments
+/// (`{ a, b ? default }: ...`) may also carry a default value.
+#[derive(Debug, Clone)]
+struct LambdaArg {
+    name: String,
+
+    /// Short rendering of the argument's default value expression, if
+    /// it has one. Only ever set for pattern arguments.
+    default: Option<String>,
 }
 
 #[derive(Debug)]
 struct DocItem {
     name: String,
     comment: DocComment,
-    args: Vec<String>,
+    args: Vec<LambdaArg>,
+
+    /// Whether the function's pattern argument ends in an ellipsis
+    /// (`{ a, ... }:`), i.e. accepts arguments beyond the ones listed.
+    args_open: bool,
+
+    /// Byte offset of the identifier in the source file, used to
+    /// resolve a source line number for `locations.xml`.
+    location: usize,
 }
 
 /// Represents a single manual section describing a library function.
@@ -68,15 +137,22 @@ struct ManualEntry {
     /// type signature in any way.
     fn_type: Option<String>,
 
-    /// Primary description of the entry. Each entry is written as a
-    /// separate paragraph.
-    description: Vec<String>,
+    /// Primary description of the entry, as raw CommonMark. Rendered
+    /// to DocBook markup when the section is written out.
+    description: String,
 
-    /// Usage example for the entry.
-    example: Option<String>,
+    /// Usage examples for the entry.
+    examples: Vec<Example>,
 
     /// Arguments of the function
-    args: Vec<String>,
+    args: Vec<LambdaArg>,
+
+    /// Whether the function accepts extra, unlisted arguments (a
+    /// pattern argument ending in `...`).
+    args_open: bool,
+
+    /// Line number of the entry in its source file.
+    line: usize,
 }
 
 impl ManualEntry {
@@ -104,36 +180,54 @@ impl ManualEntry {
             w.write(XmlEvent::end_element())?;
         }
 
-        // Include link to function location (location information is
-        // generated by a separate script in nixpkgs)
+        // Include link to function location, generated into
+        // `locations.xml` by this tool's `--locations` flag.
+        let location_id = format!("function-location-{}", ident);
         w.write(XmlEvent::start_element("xi:include")
                 .attr("href", "./locations.xml")
-                .attr("xpointer", &ident))?;
+                .attr("xpointer", location_id.as_str()))?;
         w.write(XmlEvent::end_element())?;
 
-        // Primary doc string
-        // TODO: Split paragraphs?
-        for paragraph in &self.description {
-            w.write(XmlEvent::start_element("para"))?;
-            w.write(XmlEvent::characters(paragraph))?;
-            w.write(XmlEvent::end_element())?;
-        }
+        // Primary doc string, rendered from CommonMark into DocBook.
+        commonmark::write_markdown(w, &self.description)?;
 
         // Function argument names
-        if !self.args.is_empty() {
+        if !self.args.is_empty() || self.args_open {
             w.write(XmlEvent::start_element("variablelist"))?;
             for arg in &self.args {
                 w.write(XmlEvent::start_element("varlistentry"))?;
 
                 w.write(XmlEvent::start_element("term"))?;
                 w.write(XmlEvent::start_element("varname"))?;
-                w.write(XmlEvent::characters(arg))?;
+                w.write(XmlEvent::characters(&arg.name))?;
                 w.write(XmlEvent::end_element())?;
                 w.write(XmlEvent::end_element())?;
 
                 w.write(XmlEvent::start_element("listitem"))?;
                 w.write(XmlEvent::start_element("para"))?;
-                w.write(XmlEvent::characters("Function argument"))?;
+                match &arg.default {
+                    Some(default) => w.write(XmlEvent::characters(
+                        &format!("Optional, defaults to `{}`.", default)))?,
+                    None => w.write(XmlEvent::characters("Function argument"))?,
+                }
+                w.write(XmlEvent::end_element())?;
+                w.write(XmlEvent::end_element())?;
+
+                w.write(XmlEvent::end_element())?;
+            }
+
+            if self.args_open {
+                w.write(XmlEvent::start_element("varlistentry"))?;
+
+                w.write(XmlEvent::start_element("term"))?;
+                w.write(XmlEvent::start_element("varname"))?;
+                w.write(XmlEvent::characters("..."))?;
+                w.write(XmlEvent::end_element())?;
+                w.write(XmlEvent::end_element())?;
+
+                w.write(XmlEvent::start_element("listitem"))?;
+                w.write(XmlEvent::start_element("para"))?;
+                w.write(XmlEvent::characters("Accepts additional arguments not listed here."))?;
                 w.write(XmlEvent::end_element())?;
                 w.write(XmlEvent::end_element())?;
 
@@ -143,27 +237,34 @@ impl ManualEntry {
             w.write(XmlEvent::end_element())?;
         }
 
-        // Example program listing (if applicable)
-        //
-        // TODO: In grhmc's version there are multiple (named)
-        // examples, how can this be achieved automatically?
-        if let Some(example) = &self.example {
+        // Example program listings, one `<example>` per named or
+        // unnamed `Example:` block.
+        for example in &self.examples {
             w.write(XmlEvent::start_element("example"))?;
 
             w.write(XmlEvent::start_element("title"))?;
-
-            w.write(XmlEvent::start_element("function"))?;
-            w.write(XmlEvent::characters(ident.as_str()))?;
-            w.write(XmlEvent::end_element())?;
-
-            w.write(XmlEvent::characters(" usage example"))?;
-            w.write(XmlEvent::end_element())?;
+            match &example.title {
+                Some(title) => w.write(XmlEvent::characters(title))?,
+                None => {
+                    w.write(XmlEvent::start_element("function"))?;
+                    w.write(XmlEvent::characters(ident.as_str()))?;
+                    w.write(XmlEvent::end_element())?;
+                    w.write(XmlEvent::characters(" usage example"))?;
+                }
+            }
+            w.write(XmlEvent::end_element())?; // </title>
 
             w.write(XmlEvent::start_element("programlisting"))?;
-            w.write(XmlEvent::cdata(example))?;
+            w.write(XmlEvent::cdata(&example.code))?;
             w.write(XmlEvent::end_element())?;
 
-            w.write(XmlEvent::end_element())?;
+            if let Some(result) = &example.result {
+                w.write(XmlEvent::start_element("programlisting"))?;
+                w.write(XmlEvent::cdata(result))?;
+                w.write(XmlEvent::end_element())?;
+            }
+
+            w.write(XmlEvent::end_element())?; // </example>
         }
 
         // </section>
@@ -171,15 +272,59 @@ impl ManualEntry {
 
         Ok(())
     }
+
+    /// Write a single Markdown entry for a documented Nix function.
+    fn write_section_markdown<W: Write>(&self, w: &mut W) -> Result<()> {
+        let ident = format!("lib.{}.{}", self.category, self.name);
+
+        writeln!(w, "## {}\n", ident)?;
+
+        if let Some(t) = &self.fn_type {
+            writeln!(w, "`{}`\n", t)?;
+        }
+
+        writeln!(w, "{}\n", self.description)?;
+
+        if !self.args.is_empty() || self.args_open {
+            for arg in &self.args {
+                match &arg.default {
+                    Some(default) => writeln!(w, "- `{}`: optional, defaults to `{}`", arg.name, default)?,
+                    None => writeln!(w, "- `{}`: function argument", arg.name)?,
+                }
+            }
+            if self.args_open {
+                writeln!(w, "- `...`: accepts additional arguments not listed here")?;
+            }
+            writeln!(w)?;
+        }
+
+        for example in &self.examples {
+            match &example.title {
+                Some(title) => writeln!(w, "Example: {}\n", title)?,
+                None => writeln!(w, "Example:\n")?,
+            }
+            writeln!(w, "```nix\n{}\n```", example.code)?;
+
+            if let Some(result) = &example.result {
+                writeln!(w, "\nResult:\n")?;
+                writeln!(w, "```\n{}\n```", result)?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
-/// Retrieve documentation comments. For now only multiline comments
-/// starting with `@doc` are considered.
+/// Retrieve documentation comments. Only multiline comments written as
+/// `/** ... */` (i.e. whose content begins with the extra `*` of the
+/// second asterisk) are considered documentation; plain `/* ... */`
+/// comments are treated as ordinary, non-documenting implementation
+/// comments and skipped.
 fn retrieve_doc_comment(meta: &Meta) -> Option<String> {
     for item in meta.leading.iter() {
         if let Trivia::Comment { multiline, content, .. } = item {
-            if *multiline { //  && content.as_str().starts_with(" @doc") {
-                return Some(content.to_string())
+            if *multiline && content.starts_with('*') {
+                return Some(dedent_comment(content))
             }
         }
     }
@@ -187,6 +332,59 @@ fn retrieve_doc_comment(meta: &Meta) -> Option<String> {
     return None;
 }
 
+/// Dedent the raw content of a `/** ... */` comment.
+///
+/// The content handed in always starts with the second `*` of the
+/// opening `/**` itself (that's how `retrieve_doc_comment` tells a doc
+/// comment from a plain one), which is never user content, so it's
+/// peeled off unconditionally first.
+///
+/// What's left is then dedented by stripping the minimum common
+/// leading whitespace shared by all non-blank lines (blank lines are
+/// ignored when computing that minimum, so an empty first line right
+/// after `/**` doesn't force everything else flush left). Finally, a
+/// `*`-column comment margin (as in `/**\n * foo\n */`) is peeled off
+/// too, but only when it shows up on every non-blank line — a single
+/// `* item` amid other lines is Markdown bullet-list syntax (chunk0-1
+/// renders it as such) and must survive untouched.
+fn dedent_comment(content: &str) -> String {
+    let content = content.strip_prefix('*').unwrap_or(content);
+    let lines: Vec<&str> = content.lines().collect();
+
+    let min_indent = lines.iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    let dedented: Vec<&str> = lines.iter()
+        .map(|line| {
+            if line.len() >= min_indent {
+                &line[min_indent..]
+            } else {
+                line.trim_start()
+            }
+        })
+        .collect();
+
+    let has_margin = dedented.iter()
+        .filter(|line| !line.trim().is_empty())
+        .all(|line| line.starts_with('*'));
+
+    if !has_margin {
+        return dedented.join("\n");
+    }
+
+    dedented.iter()
+        .map(|line| {
+            line.strip_prefix("* ")
+                .or_else(|| line.strip_prefix('*'))
+                .unwrap_or(line)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 /// Transforms an AST node into a `DocItem` if it has a leading
 /// documentation comment.
 fn retrieve_doc_item(node: &ASTNode) -> Option<DocItem> {
@@ -198,6 +396,8 @@ fn retrieve_doc_item(node: &ASTNode) -> Option<DocItem> {
             name: name.to_string(),
             comment: parse_doc_comment(&comment),
             args: vec![],
+            args_open: false,
+            location: meta.span.start.into(),
         })
     }
 
@@ -208,90 +408,217 @@ fn retrieve_doc_item(node: &ASTNode) -> Option<DocItem> {
 fn parse_doc_comment(raw: &str) -> DocComment {
     enum ParseState { Doc, Type, Example }
 
+    // Accumulates the `Example:` block currently being parsed, until
+    // the next `Example:` or `Type:` marker (or the end of the comment)
+    // closes it off into `examples`.
+    struct ExampleBuilder {
+        title: Option<String>,
+        code: String,
+        result: Option<String>,
+        in_result: bool,
+    }
+
     let mut doc = String::new();
     let mut doc_type = String::new();
-    let mut example = String::new();
+    let mut examples: Vec<Example> = vec![];
+    let mut current: Option<ExampleBuilder> = None;
     let mut state = ParseState::Doc;
 
-    for line in raw.trim().lines() {
-        let mut line = line.trim();
-
-        if line.starts_with("@doc ") {
+    let finish_example = |current: &mut Option<ExampleBuilder>, examples: &mut Vec<Example>| {
+        if let Some(b) = current.take() {
+            let code = b.code.trim().to_string();
+            let result = b.result.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+            if !code.is_empty() || result.is_some() {
+                examples.push(Example { title: b.title, code, result });
+            }
+        }
+    };
+
+    for raw_line in raw.trim().lines() {
+        // `trimmed` is used to detect control markers (`@doc`,
+        // `Type:`, `Example:`) regardless of indentation. `line`
+        // starts out pointing at the same text, but a marker can
+        // rewrite it to the remainder after the marker; `rewrote`
+        // tracks that so the `Doc` arm below knows whether to fall
+        // back to `raw_line` (preserving indentation for the
+        // CommonMark renderer) or use the marker-stripped `line`.
+        let trimmed = raw_line.trim();
+        let mut line = trimmed;
+        let mut rewrote = false;
+
+        if trimmed.starts_with("@doc ") {
             state = ParseState::Doc;
-            line = line.trim_start_matches("@doc ");
+            line = trimmed.trim_start_matches("@doc ");
+            rewrote = true;
         }
 
-        if line.starts_with("Type:") {
+        if trimmed.starts_with("Type:") {
+            finish_example(&mut current, &mut examples);
             state = ParseState::Type;
-            line = &line[5..]; //.trim_start_matches("Type:");
+            line = &trimmed[5..]; //.trim_start_matches("Type:");
+            rewrote = true;
         }
 
-        if line.starts_with("Example:") {
+        if trimmed.starts_with("Example:") {
+            finish_example(&mut current, &mut examples);
             state = ParseState::Example;
-            line = line.trim_start_matches("Example:");
+            let title = trimmed.trim_start_matches("Example:").trim();
+            current = Some(ExampleBuilder {
+                title: if title.is_empty() { None } else { Some(title.to_string()) },
+                code: String::new(),
+                result: None,
+                in_result: false,
+            });
+            continue;
         }
 
         match state {
             ParseState::Type => doc_type.push_str(line.trim()),
             ParseState::Doc => {
-                doc.push_str(line.trim());
+                // Keep the line's own indentation (beyond the doc
+                // comment's common dedent) so fenced code blocks and
+                // nested lists reach `commonmark::write_markdown`
+                // with their structure intact.
+                doc.push_str(if rewrote { line } else { raw_line });
                 doc.push('\n');
             },
             ParseState::Example => {
-                example.push_str(line.trim());
-                example.push('\n');
+                if let Some(b) = &mut current {
+                    let separator = trimmed.strip_prefix("=>")
+                        .or_else(|| trimmed.strip_prefix("Result:"));
+
+                    if let Some(rest) = separator.filter(|_| !b.in_result) {
+                        b.in_result = true;
+                        let rest = rest.trim();
+                        if !rest.is_empty() {
+                            let result = b.result.get_or_insert_with(String::new);
+                            result.push_str(rest);
+                            result.push('\n');
+                        }
+                    } else if b.in_result {
+                        let result = b.result.get_or_insert_with(String::new);
+                        result.push_str(if rewrote { line } else { raw_line });
+                        result.push('\n');
+                    } else {
+                        // Keep indentation here too, for the same reason as
+                        // the `Doc` arm above: example code is rendered as a
+                        // `<programlisting>`/code block, where indentation
+                        // is part of the content.
+                        b.code.push_str(if rewrote { line } else { raw_line });
+                        b.code.push('\n');
+                    }
+                }
             },
         }
     }
 
+    finish_example(&mut current, &mut examples);
+
     let f = |s: String| if s.is_empty() { None } else { Some(s.into()) };
 
     DocComment {
         doc: doc.trim().into(),
         doc_type: f(doc_type),
-        example: f(example),
+        examples,
     }
 }
 
-/// Traverse a Nix lambda and collect the identifiers of arguments
-/// until an unexpected AST node is encountered.
+/// Traverse a Nix lambda and collect its arguments until an
+/// unexpected AST node is encountered.
 ///
-/// This will collect the argument names for curried functions in the
-/// `a: b: c: ...`-style, but does not currently work with pattern
-/// functions (`{ a, b, c }: ...`).
+/// This handles both curried functions in the `a: b: c: ...`-style
+/// and pattern functions (`{ a, b ? default, ... }: ...`), recursing
+/// into the body when a pattern is itself followed by a curried
+/// argument (`{ ... }: x: ...`).
 ///
 /// In the AST representation used by rnix, any lambda node has an
-/// immediate child that is the identifier of its argument. The "body"
-/// of the lambda is two steps to the right from that identifier, if
-/// it is a lambda the function is curried and we can recurse.
-fn collect_lambda_args<'a>(arena: &Arena<'a>,
+/// immediate child that is either the identifier or the pattern of
+/// its argument. The "body" of the lambda is two steps to the right
+/// from that child; if it is itself a lambda the function is curried
+/// and we can recurse.
+fn collect_lambda_args<'a>(src: &str,
+                           arena: &Arena<'a>,
                            lambda_node: &ASTNode,
-                           args: &mut Vec<String>) -> Option<()> {
-    let ident_node = &arena[lambda_node.node.child?];
-    if let Data::Ident(_, name) = &ident_node.data {
-        args.push(name.to_string());
+                           args: &mut Vec<LambdaArg>,
+                           args_open: &mut bool) -> Option<()> {
+    let head_node = &arena[lambda_node.node.child?];
+
+    match &head_node.data {
+        Data::Ident(_, name) => args.push(LambdaArg { name: name.to_string(), default: None }),
+        _ if head_node.kind == ASTKind::Pattern => {
+            collect_pattern_args(src, arena, head_node, args, args_open);
+        }
+        _ => (),
     }
 
     // Two to the right ...
-    let token_node = &arena[ident_node.node.sibling?];
+    let token_node = &arena[head_node.node.sibling?];
     let body_node = &arena[token_node.node.sibling?];
 
     // Curried or not?
     if body_node.kind == ASTKind::Lambda {
-        collect_lambda_args(arena, body_node, args);
+        collect_lambda_args(src, arena, body_node, args, args_open);
     }
 
     Some(())
 }
 
+/// Collect the entries of a pattern function argument
+/// (`{ a, b ? default, ... }:`). Each entry is a `PatEntry` node
+/// carrying the argument's identifier, then (when a default is given)
+/// the `?` token, then the default value expression as its sibling.
+/// An `Ellipsis` node among the pattern's children marks the pattern
+/// as open (`...`).
+fn collect_pattern_args<'a>(src: &str,
+                            arena: &Arena<'a>,
+                            pattern_node: &ASTNode,
+                            args: &mut Vec<LambdaArg>,
+                            args_open: &mut bool) -> Option<()> {
+    let mut next = pattern_node.node.child;
+
+    while let Some(idx) = next {
+        let entry_node = &arena[idx];
+
+        match entry_node.kind {
+            ASTKind::PatEntry => {
+                if let Some(ident_idx) = entry_node.node.child {
+                    let ident_node = &arena[ident_idx];
+                    if let Data::Ident(_, name) = &ident_node.data {
+                        // `?` token, then the default expression itself.
+                        let default = ident_node.node.sibling
+                            .and_then(|question_mark| arena[question_mark].node.sibling)
+                            .map(|idx| render_default(src, &arena[idx]));
+                        args.push(LambdaArg { name: name.to_string(), default });
+                    }
+                }
+            }
+            ASTKind::Ellipsis => *args_open = true,
+            _ => (),
+        }
+
+        next = entry_node.node.sibling;
+    }
+
+    Some(())
+}
+
+/// Render a default-value expression as its original Nix source text,
+/// by slicing `src` at the node's span, so e.g. `5` stays `5` and
+/// `"foo"` stays `"foo"` instead of rnix's `Debug` form.
+fn render_default(src: &str, node: &ASTNode) -> String {
+    let start: usize = node.node.span.start.into();
+    let end: usize = node.node.span.end.map(Into::into).unwrap_or_else(|| src.len());
+    src[start..end].trim().to_string()
+}
+
 /// Traverse the arena from a top-level SetEntry and collect, where
 /// possible:
 ///
 /// 1. The identifier of the set entry itself.
 /// 2. The attached doc comment on the entry.
-/// 3. The argument names of any curried functions (pattern functions
-///    not yet supported).
-fn collect_entry_information<'a>(arena: &Arena<'a>, entry_node: &ASTNode) -> Option<DocItem> {
+/// 3. The argument names of any curried or pattern functions, along
+///    with defaults and openness for pattern arguments.
+fn collect_entry_information<'a>(src: &str, arena: &Arena<'a>, entry_node: &ASTNode) -> Option<DocItem> {
     // The "root" of any attribute set entry is this `SetEntry` node.
     // It has an `Attribute` child, which in turn has the identifier
     // (on which the documentation comment is stored) as its child.
@@ -310,14 +637,21 @@ fn collect_entry_information<'a>(arena: &Arena<'a>, entry_node: &ASTNode) -> Opt
     let content_node = &arena[assign_node.node.sibling?];
 
     if content_node.kind == ASTKind::Lambda {
-        let mut args: Vec<String> = vec![];
-        collect_lambda_args(arena, content_node, &mut args);
-        Some(DocItem { args, ..doc_item })
+        let mut args: Vec<LambdaArg> = vec![];
+        let mut args_open = false;
+        collect_lambda_args(src, arena, content_node, &mut args, &mut args_open);
+        Some(DocItem { args, args_open, ..doc_item })
     } else {
         Some(doc_item)
     }
 }
 
+/// Convert a byte offset into `src` to a 1-based line number, by
+/// counting newlines up to that offset.
+fn offset_to_line(src: &str, offset: usize) -> usize {
+    src[..offset].matches('\n').count() + 1
+}
+
 fn main() {
     let opts = Options::from_args();
     let src = fs::read_to_string(&opts.file).unwrap();
@@ -325,39 +659,35 @@ fn main() {
 
     let entries: Vec<ManualEntry> = nix.arena.into_iter()
         .filter(|node| node.kind == ASTKind::SetEntry)
-        .filter_map(|node| collect_entry_information(&nix.arena, node))
+        .filter_map(|node| collect_entry_information(&src, &nix.arena, node))
         .map(|d| ManualEntry {
             category: opts.category.clone(),
             name: d.name,
-            description: d.comment.doc
-                .split("\n\n")
-                .map(|s| s.to_string())
-                .collect(),
+            description: d.comment.doc,
             fn_type: d.comment.doc_type,
-            example: d.comment.example,
+            examples: d.comment.examples,
             args: d.args,
+            args_open: d.args_open,
+            line: offset_to_line(&src, d.location),
         })
         .collect();
 
-    let mut writer = EmitterConfig::new()
-        .perform_indent(true)
-        .create_writer(io::stdout());
+    if let Some(locations_path) = &opts.locations {
+        let out = fs::File::create(locations_path).unwrap();
+        locations::write_locations(out, &opts.file.to_string_lossy(), &entries)
+            .expect("Failed to write locations.xml");
+    }
 
-    writer.write(
-        XmlEvent::start_element("section")
-            .attr("xmlns", "http://docbook.org/ns/docbook")
-            .attr("xmlns:xlink", "http://www.w3.org/1999/xlink")
-            .attr("xmlns:xi", "http://www.w3.org/2001/XInclude")
-            .attr("xml:id", format!("sec-functions-library-{}", opts.category).as_str()))
-        .unwrap();
+    let mut writer: Box<dyn DocFormat> = match opts.format {
+        Format::Docbook => Box::new(format::DocbookWriter::new(opts.category.clone(), io::stdout())),
+        Format::Markdown => Box::new(format::MarkdownWriter::new(io::stdout())),
+    };
 
-    writer.write(XmlEvent::start_element("title")).unwrap();
-    writer.write(XmlEvent::characters(&opts.description)).unwrap();
-    writer.write(XmlEvent::end_element()).unwrap();
+    writer.write_header(&opts.description).unwrap();
 
-    for entry in entries {
-        entry.write_section_xml(&mut writer).expect("Failed to write section")
+    for entry in &entries {
+        writer.write_entry(entry).expect("Failed to write entry")
     }
 
-    writer.write(XmlEvent::end_element()).unwrap();
+    writer.write_footer().unwrap();
 }